@@ -81,6 +81,119 @@ pub mod bitmap {
         }
     }
 
+    /**
+     * Converts u32 number as sequance of big-endian bytes (as used by PNG)
+     */
+    fn u32_to_u8_be(x : u32) -> Vec<u8> {
+        vec![(x / (256 * 256 * 256) % 256) as u8, (x / (256 * 256) % 256) as u8, (x / 256 % 256) as u8, (x % 256) as u8]
+    }
+
+    /**
+     * Builds the standard CRC32 lookup table by folding each index 8 times
+     */
+    fn crc_table() -> [u32; 256] {
+        fn crc_accum(a : u32) -> u32 {
+            if a & 1 == 1 { 0xEDB88320 ^ (a >> 1) } else { a >> 1 }
+        }
+        let mut t = [0u32; 256];
+        for (i, e) in t.iter_mut().enumerate() {
+            let mut a = i as u32;
+            for _ in 0 .. 8 {
+                a = crc_accum(a);
+            }
+            *e = a;
+        }
+        t
+    }
+
+    /**
+     * Computes the CRC32 of a buffer using the standard PNG polynomial
+     */
+    fn crc32(buf : &[u8]) -> u32 {
+        let t = crc_table();
+        !buf.iter().fold(0xFFFFFFFFu32, |a, &b| (a >> 8) ^ t[((a ^ b as u32) & 0xFF) as usize])
+    }
+
+    /**
+     * Computes the Adler-32 checksum of a buffer (used by zlib streams)
+     */
+    fn adler32(buf : &[u8]) -> u32 {
+        let mut a : u32 = 1;
+        let mut b : u32 = 0;
+        for &byte in buf {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        (b << 16) | a
+    }
+
+    /**
+     * Wraps raw bytes in a zlib stream built out of DEFLATE stored (uncompressed) blocks
+     */
+    fn zlib_stored(raw : &[u8]) -> Vec<u8> {
+        let mut res = vec![0x78, 0x01]; // zlib header
+        let chunks : Vec<&[u8]> = if raw.is_empty() {
+            vec![&raw[0..0]]
+        } else {
+            raw.chunks(65535).collect()
+        };
+        for (i, chunk) in chunks.iter().enumerate() {
+            let final_block = i == chunks.len() - 1;
+            res.push(if final_block { 0x01 } else { 0x00 });
+            let len = chunk.len() as u16;
+            res.append(&mut u16_to_u8(len));
+            res.append(&mut u16_to_u8(!len));
+            res.extend_from_slice(chunk);
+        }
+        res.append(&mut u32_to_u8_be(adler32(raw)));
+        res
+    }
+
+    /**
+     * Builds a PNG chunk: length, type, data and CRC32 over type+data
+     */
+    fn png_chunk(kind : &[u8; 4], data : &[u8]) -> Vec<u8> {
+        let mut res = u32_to_u8_be(data.len() as u32);
+        let mut type_and_data = kind.to_vec();
+        type_and_data.extend_from_slice(data);
+        res.extend_from_slice(&type_and_data);
+        res.append(&mut u32_to_u8_be(crc32(&type_and_data)));
+        res
+    }
+
+    /**
+     * Checked little-endian reads over a byte buffer; every accessor bails with a
+     * descriptive error instead of panicking when the buffer is too short
+     */
+    trait ByteReader {
+        fn c_u8(&self, i : usize) -> Result<u8, String>;
+        fn c_u16l(&self, i : usize) -> Result<u16, String>;
+        fn c_u32l(&self, i : usize) -> Result<u32, String>;
+    }
+
+    impl ByteReader for [u8] {
+        fn c_u8(&self, i : usize) -> Result<u8, String> {
+            if i + 1 > self.len() {
+                return Err(String::from("not enough data"));
+            }
+            Ok(self[i])
+        }
+
+        fn c_u16l(&self, i : usize) -> Result<u16, String> {
+            if i + 2 > self.len() {
+                return Err(String::from("not enough data"));
+            }
+            Ok(self[i] as u16 | (self[i + 1] as u16) << 8)
+        }
+
+        fn c_u32l(&self, i : usize) -> Result<u32, String> {
+            if i + 4 > self.len() {
+                return Err(String::from("not enough data"));
+            }
+            Ok(self[i] as u32 | (self[i + 1] as u32) << 8 | (self[i + 2] as u32) << 16 | (self[i + 3] as u32) << 24)
+        }
+    }
+
     impl BitMap {
         /**
          * Creates BitMap out of vector of pixles
@@ -208,6 +321,233 @@ pub mod bitmap {
             }
         }
         
+        /**
+         * Saves bitmap to specified file as a lossless 24-bit (truecolor) PNG
+         */
+        pub fn save_as_png<P>(&self, path: P) -> Result<(), String> where P : AsRef<Path>{
+            let mut raw = vec![];
+            for y in 0..self.height as usize {
+                raw.push(0); // filter byte: None
+                for x in 0..self.width as usize {
+                    raw.push(self.data[x][y].red);
+                    raw.push(self.data[x][y].green);
+                    raw.push(self.data[x][y].blue);
+                }
+            }
+
+            let mut ihdr = u32_to_u8_be(self.width);
+            ihdr.append(&mut u32_to_u8_be(self.height));
+            ihdr.push(8); // bit depth
+            ihdr.push(2); // color type: truecolor
+            ihdr.extend_from_slice(&[0, 0, 0]); // compression, filter, interlace
+
+            let mut res = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+            res.append(&mut png_chunk(b"IHDR", &ihdr));
+            res.append(&mut png_chunk(b"IDAT", &zlib_stored(&raw)));
+            res.append(&mut png_chunk(b"IEND", &[]));
+
+            match fs::write(path, res) {
+                Ok(()) => Ok(()),
+                Err(e) => Err(e.to_string())
+            }
+        }
+
+        /**
+         * Reads a 24-bit uncompressed .bmp file back into a BitMap, so rendered plots (or
+         * externally supplied bitmaps to draw onto) can be round-tripped
+         */
+        pub fn load_from_bmp<P>(path: P) -> Result<BitMap, String> where P : AsRef<Path>{
+            let buf = fs::read(path).map_err(|e| e.to_string())?;
+            if buf.c_u8(0)? != b'B' || buf.c_u8(1)? != b'M' {
+                return Err(String::from("not a BMP file (missing \"BM\" magic)"));
+            }
+            let offset = buf.c_u32l(10)? as usize; // offset to pixel data
+            let info_len = buf.c_u32l(14)?;
+            if info_len < 40 {
+                return Err(String::from("unsupported BMP info header"));
+            }
+            let width = buf.c_u32l(18)?;
+            let height = buf.c_u32l(22)?;
+            let bit_count = buf.c_u16l(28)?;
+            let compression = buf.c_u32l(30)?;
+            if bit_count != 24 {
+                return Err(format!("unsupported bit count {} (only 24 is supported)", bit_count));
+            }
+            if compression != 0 {
+                return Err(format!("unsupported compression {} (only uncompressed is supported)", compression));
+            }
+
+            let row_size = (width as usize * 3) + offset_to(width as usize * 3, 4);
+            let mut data = vec![vec![Pixel::BLACK; height as usize]; width as usize];
+            for r in 0..height as usize {
+                let y = height as usize - 1 - r; // rows are stored bottom-up
+                let row_start = offset + r * row_size;
+                for x in 0..width as usize {
+                    let p = row_start + x * 3;
+                    let blue = buf.c_u8(p)?;
+                    let green = buf.c_u8(p + 1)?;
+                    let red = buf.c_u8(p + 2)?;
+                    data[x][y] = Pixel::new(red, green, blue);
+                }
+            }
+            BitMap::new(data)
+        }
+
+        /**
+         * Retruns size of bitmap
+         */
+        pub fn size(&self) -> (u32, u32) {
+            (self.width, self.height)
+        }
+    }
+
+    /**
+     * Struct of an indexed (paletted) bitmap: every pixel is an index into palette
+     */
+    #[derive(Debug)]
+    pub struct IndexedBitMap {
+        width : u32,
+        height : u32,
+        index : Vec<Vec<u8>>,
+        palette : Vec<Pixel>,
+    }
+
+    /**
+     * Encodes a single scanline of indices in BI_RLE8, terminated by an end-of-line marker
+     */
+    fn encode_rle8_row(row : &[u8]) -> Vec<u8> {
+        let mut res = vec![];
+        let n = row.len();
+        let mut i = 0;
+        while i < n {
+            let mut run = 1;
+            while i + run < n && row[i + run] == row[i] && run < 255 {
+                run += 1;
+            }
+            if run >= 2 {
+                res.push(run as u8);
+                res.push(row[i]);
+                i += run;
+            } else {
+                // absolute mode: gather literals until a run of >= 3 appears
+                let mut lit = vec![];
+                while i < n && lit.len() < 255 {
+                    let mut r = 1;
+                    while i + r < n && row[i + r] == row[i] && r < 3 {
+                        r += 1;
+                    }
+                    if r >= 3 {
+                        break;
+                    }
+                    lit.push(row[i]);
+                    i += 1;
+                }
+                if lit.len() >= 3 {
+                    res.push(0x00);
+                    res.push(lit.len() as u8);
+                    res.extend_from_slice(&lit);
+                    if lit.len() % 2 == 1 { // pad run to a 16-bit boundary
+                        res.push(0x00);
+                    }
+                } else {
+                    for &b in &lit {
+                        res.push(1);
+                        res.push(b);
+                    }
+                }
+            }
+        }
+        res.push(0x00); // end of line
+        res.push(0x00);
+        res
+    }
+
+    impl IndexedBitMap {
+        /**
+         * Creates an indexed bitmap out of a grid of palette indices and a palette
+         */
+        pub fn new(index : Vec<Vec<u8>>, palette : Vec<Pixel>) -> Result<IndexedBitMap, String> {
+            if palette.len() > 256 {
+                return Err(String::from("Palette has more than 256 colors"));
+            }
+            let w = index.len() as u32;
+            if w == 0 {
+                return Ok(IndexedBitMap {index, palette, width: 0, height: 0});
+            }
+            let h = index[0].len() as u32;
+            for col in index.iter().skip(1) {
+                if col.len() != h as usize {
+                    return Err(String::from("Not a rectangle"));
+                }
+            }
+            Ok(IndexedBitMap {index, palette, width: w, height: h})
+        }
+
+        /**
+         * Creates an indexed bitmap whose indices are produced by a generator
+         */
+        pub fn new_from_generator(gen: &dyn Fn(u32, u32) -> u8, palette : Vec<Pixel>, width: u32, height: u32) -> IndexedBitMap {
+            let mut index = vec![vec![0u8; height as usize]; width as usize];
+            for x in 0 .. width {
+                for y in 0 .. height {
+                    index[x as usize][y as usize] = gen(x, y);
+                }
+            }
+            IndexedBitMap {index, palette, width, height}
+        }
+
+        /**
+         * Creates info header for an 8-bit RLE-compressed .bmp file
+         */
+        fn get_rle_info_header(width : u32, height : u32, size_image : u32, colors : u32) -> Vec<u8> {
+            let mut res = vec![];
+            res.append(&mut u32_to_u8(40));         // length of info header
+            res.append(&mut u32_to_u8(width));      // width of picture
+            res.append(&mut u32_to_u8(height));     // height of picture
+            res.append(&mut u16_to_u8(1));          // num of planes
+            res.append(&mut u16_to_u8(8));          // bits of colors per pixel
+            res.append(&mut u32_to_u8(1));          // compression (BI_RLE8)
+            res.append(&mut u32_to_u8(size_image)); // compressed image size
+            res.append(&mut u32_to_u8(100));        // pixels per meter (x-axis)
+            res.append(&mut u32_to_u8(100));        // pixels per meter (y-axis)
+            res.append(&mut u32_to_u8(colors));     // number of colors used
+            res.append(&mut u32_to_u8(0));          // number of important colors
+            res
+        }
+
+        /**
+         * Saves the indexed bitmap to specified file as a BI_RLE8-compressed .bmp
+         */
+        pub fn save_as_rle_bmp<P>(&self, path: P) -> Result<(), String> where P : AsRef<Path>{
+            let mut body = vec![];
+            for y in (0..self.height as usize).rev() { // bottom-up scanlines
+                let row : Vec<u8> = (0..self.width as usize).map(|x| self.index[x][y]).collect();
+                body.append(&mut encode_rle8_row(&row));
+            }
+            body.push(0x00); // end of bitmap
+            body.push(0x01);
+
+            let color_table_len = self.palette.len() as u32 * 4;
+            let offset = 14 + 40 + color_table_len;
+            let mut res = BitMap::get_header(offset + body.len() as u32);
+            // get_header writes a fixed offset of 54, patch it to include the color table
+            let off_bytes = u32_to_u8(offset);
+            res[10..14].copy_from_slice(&off_bytes);
+            res.append(&mut IndexedBitMap::get_rle_info_header(self.width, self.height, body.len() as u32, self.palette.len() as u32));
+            for p in &self.palette {
+                res.push(p.blue);
+                res.push(p.green);
+                res.push(p.red);
+                res.push(0);
+            }
+            res.append(&mut body);
+
+            match fs::write(path, res) {
+                Ok(()) => Ok(()),
+                Err(e) => Err(e.to_string())
+            }
+        }
+
         /**
          * Retruns size of bitmap
          */
@@ -233,4 +573,156 @@ pub mod bitmap {
             &mut self.data[x as usize][y as usize]
         }
     }
+
+    #[cfg(test)]
+    mod tests_png {
+        use super::*;
+
+        /**
+         * Decodes a zlib stream of DEFLATE stored blocks back to the raw bytes (test helper)
+         */
+        fn inflate_stored(stream : &[u8]) -> Vec<u8> {
+            let mut out = vec![];
+            let mut i = 2; // skip the 2-byte zlib header
+            loop {
+                let final_block = stream[i] == 0x01;
+                let len = stream[i + 1] as usize | (stream[i + 2] as usize) << 8;
+                i += 5; // block header byte + LEN + ~LEN
+                out.extend_from_slice(&stream[i .. i + len]);
+                i += len;
+                if final_block {
+                    break;
+                }
+            }
+            out
+        }
+
+        #[test]
+        fn crc32_matches_known_vectors() {
+            assert_eq!(crc32(b"123456789"), 0xCBF4_3926); // canonical CRC-32 check value
+            assert_eq!(crc32(b"IEND"), 0xAE42_6082);      // the fixed CRC of an empty IEND chunk
+        }
+
+        #[test]
+        fn adler32_matches_known_vectors() {
+            assert_eq!(adler32(b""), 1);
+            assert_eq!(adler32(&[0u8]), 0x0001_0001);
+        }
+
+        #[test]
+        fn zlib_stored_round_trips() {
+            let raw : Vec<u8> = (0..1000u32).map(|v| (v % 256) as u8).collect();
+            let stream = zlib_stored(&raw);
+            assert_eq!(&stream[0..2], &[0x78, 0x01]);
+            assert_eq!(inflate_stored(&stream), raw);
+            let tail = &stream[stream.len() - 4 ..];
+            assert_eq!(u32::from_be_bytes([tail[0], tail[1], tail[2], tail[3]]), adler32(&raw));
+        }
+
+        #[test]
+        fn png_header_is_well_formed() {
+            let bm = BitMap::new_blank(Pixel::RED, 3, 2);
+            let path = std::env::temp_dir().join("mandelbrot_test_header.png");
+            bm.save_as_png(&path).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            let _ = fs::remove_file(&path);
+            assert_eq!(&bytes[0..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+            // IHDR width/height live right after the 8-byte signature and the chunk length+type
+            assert_eq!(&bytes[16..20], &u32_to_u8_be(3)[..]);
+            assert_eq!(&bytes[20..24], &u32_to_u8_be(2)[..]);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests_rle {
+        use super::*;
+
+        /**
+         * Decodes one BI_RLE8 scanline (up to the end-of-line marker) back to indices
+         */
+        fn decode_rle8_row(enc : &[u8]) -> Vec<u8> {
+            let mut out = vec![];
+            let mut i = 0;
+            loop {
+                let count = enc[i];
+                let value = enc[i + 1];
+                i += 2;
+                if count == 0 {
+                    if value == 0 {
+                        break; // end of line
+                    }
+                    let n = value as usize; // absolute run
+                    out.extend_from_slice(&enc[i .. i + n]);
+                    i += n;
+                    if n % 2 == 1 { // skip padding to a 16-bit boundary
+                        i += 1;
+                    }
+                } else {
+                    for _ in 0 .. count {
+                        out.push(value);
+                    }
+                }
+            }
+            out
+        }
+
+        #[test]
+        fn rle8_row_round_trips() {
+            let rows : [&[u8]; 3] = [
+                &[7, 7, 7, 7, 7, 7, 7, 7],        // single long run
+                &[1, 2, 3, 4, 5],                 // all literals (absolute mode)
+                &[9, 9, 9, 1, 2, 3, 4, 4, 4, 4],  // runs and literals mixed
+            ];
+            for row in rows {
+                assert_eq!(decode_rle8_row(&encode_rle8_row(row)), row.to_vec());
+            }
+        }
+
+        #[test]
+        fn indexed_generator_fills_indices() {
+            let bm = IndexedBitMap::new_from_generator(&|x, y| (x + y) as u8, vec![Pixel::BLACK; 4], 3, 2);
+            assert_eq!(bm.size(), (3, 2));
+            for x in 0 .. 3 {
+                for y in 0 .. 2 {
+                    assert_eq!(bm.index[x as usize][y as usize], (x + y) as u8);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests_bmp {
+        use super::*;
+
+        #[test]
+        fn bmp_round_trips() {
+            let data = vec![
+                vec![Pixel::RED, Pixel::GREEN],
+                vec![Pixel::BLUE, Pixel::WHITE],
+                vec![Pixel::BLACK, Pixel::new(1, 2, 3)],
+            ];
+            let bm = BitMap::new(data).unwrap();
+            let path = std::env::temp_dir().join("mandelbrot_test_roundtrip.bmp");
+            bm.save_as_bmp(&path).unwrap();
+            let loaded = BitMap::load_from_bmp(&path).unwrap();
+            let _ = fs::remove_file(&path);
+
+            assert_eq!(loaded.size(), bm.size());
+            for x in 0 .. bm.width {
+                for y in 0 .. bm.height {
+                    let (a, b) = (&bm[(x, y)], &loaded[(x, y)]);
+                    assert_eq!((a.red, a.green, a.blue), (b.red, b.green, b.blue));
+                }
+            }
+        }
+
+        #[test]
+        fn load_rejects_bad_magic() {
+            let path = std::env::temp_dir().join("mandelbrot_test_badmagic.bmp");
+            fs::write(&path, b"XM not a bitmap").unwrap();
+            let res = BitMap::load_from_bmp(&path);
+            let _ = fs::remove_file(&path);
+            assert!(res.is_err());
+        }
+    }
 }
\ No newline at end of file
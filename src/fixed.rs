@@ -0,0 +1,219 @@
+
+pub mod fixed {
+    use std::fmt;
+    use std::cmp::Ordering;
+    use std::ops::{Add, Sub, Mul, Div, Neg};
+
+    /**
+     * Fixed-point number backed by i128 with FRAC fractional bits: the stored value v
+     * represents the real number v / 2^FRAC. Gives far more mantissa than f64 for deep
+     * zooms where pixel sizes approach 1e-15.
+     */
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FxBig<const FRAC: u32> {
+        raw : i128,
+    }
+
+    /**
+     * Widening 128x128 -> 256-bit unsigned multiply, returned as (high, low) halves
+     */
+    fn widening_mul_u128(a : u128, b : u128) -> (u128, u128) {
+        let mask = u64::MAX as u128;
+        let (a_lo, a_hi) = (a & mask, a >> 64);
+        let (b_lo, b_hi) = (b & mask, b >> 64);
+        let ll = a_lo * b_lo;
+        let lh = a_lo * b_hi;
+        let hl = a_hi * b_lo;
+        let hh = a_hi * b_hi;
+        let cross = (ll >> 64) + (lh & mask) + (hl & mask);
+        let lo = (ll & mask) | (cross << 64);
+        let hi = hh + (lh >> 64) + (hl >> 64) + (cross >> 64);
+        (hi, lo)
+    }
+
+    impl <const FRAC: u32> FxBig<FRAC> {
+        /**
+         * Creates a fixed-point value from its raw underlying i128 representation
+         */
+        pub const fn from_raw(raw : i128) -> Self {
+            FxBig { raw }
+        }
+
+        /**
+         * Creates a fixed-point value representing the integer n
+         */
+        pub const fn from_int(n : i128) -> Self {
+            FxBig { raw: n << FRAC }
+        }
+
+        /**
+         * Creates a fixed-point value approximating the f64 v
+         */
+        pub fn from_f64(v : f64) -> Self {
+            FxBig { raw: (v * (1u128 << FRAC) as f64).round() as i128 }
+        }
+
+        /**
+         * Converts the fixed-point value back to the nearest f64
+         */
+        pub fn to_f64(self) -> f64 {
+            self.raw as f64 / (1u128 << FRAC) as f64
+        }
+    }
+
+    /**
+     * Implementation of + operator
+     */
+    impl <const FRAC: u32> Add for FxBig<FRAC> {
+        type Output = FxBig<FRAC>;
+
+        fn add(self, rhs: FxBig<FRAC>) -> Self::Output {
+            FxBig { raw: self.raw + rhs.raw }
+        }
+    }
+
+    /**
+     * Implementation of binary - operator
+     */
+    impl <const FRAC: u32> Sub for FxBig<FRAC> {
+        type Output = FxBig<FRAC>;
+
+        fn sub(self, rhs: FxBig<FRAC>) -> Self::Output {
+            FxBig { raw: self.raw - rhs.raw }
+        }
+    }
+
+    /**
+     * Implementation of unary - operator
+     */
+    impl <const FRAC: u32> Neg for FxBig<FRAC> {
+        type Output = FxBig<FRAC>;
+
+        fn neg(self) -> Self::Output {
+            FxBig { raw: -self.raw }
+        }
+    }
+
+    /**
+     * 256-bit by 128-bit unsigned long division. Returns the low 128 bits of the quotient
+     * together with a flag that is set when the true quotient does not fit in 128 bits, so
+     * the caller can saturate instead of handing back a wrapped value.
+     */
+    fn div_u256_by_u128(hi : u128, lo : u128, d : u128) -> (u128, bool) {
+        let mut rem : u128 = 0;
+        let mut quo : u128 = 0;
+        let mut overflow = false;
+        for i in (0 .. 256).rev() {
+            let bit = if i >= 128 { (hi >> (i - 128)) & 1 } else { (lo >> i) & 1 };
+            let carry = rem >> 127;
+            rem = (rem << 1) | bit;
+            if quo >> 127 == 1 {
+                overflow = true; // a set quotient bit is about to be shifted past bit 127
+            }
+            quo <<= 1;
+            if carry == 1 || rem >= d {
+                rem = rem.wrapping_sub(d);
+                quo |= 1;
+            }
+        }
+        (quo, overflow)
+    }
+
+    /**
+     * Implementation of * operator; the product is computed in a 256-bit intermediate and
+     * shifted back down by FRAC to avoid overflowing the i128 representation. Results that
+     * still do not fit saturate to the representable range rather than silently wrapping.
+     */
+    impl <const FRAC: u32> Mul for FxBig<FRAC> {
+        type Output = FxBig<FRAC>;
+
+        fn mul(self, rhs: FxBig<FRAC>) -> Self::Output {
+            let neg = (self.raw < 0) ^ (rhs.raw < 0);
+            let (hi, lo) = widening_mul_u128(self.raw.unsigned_abs(), rhs.raw.unsigned_abs());
+            let (res_hi, res_lo) = if FRAC == 0 {
+                (hi, lo)
+            } else {
+                (hi >> FRAC, (lo >> FRAC) | (hi << (128 - FRAC)))
+            };
+            let raw = if res_hi != 0 || res_lo > i128::MAX as u128 {
+                i128::MAX // magnitude exceeds the representable range
+            } else {
+                res_lo as i128
+            };
+            FxBig { raw: if neg { -raw } else { raw } }
+        }
+    }
+
+    /**
+     * Implementation of / operator: the dividend is widened to 256 bits before the shift
+     * by FRAC so no bits are lost (shifting raw in place overflows i128 for large FRAC),
+     * then divided by the divisor and saturated to the representable range
+     */
+    impl <const FRAC: u32> Div for FxBig<FRAC> {
+        type Output = FxBig<FRAC>;
+
+        fn div(self, rhs: FxBig<FRAC>) -> Self::Output {
+            let neg = (self.raw < 0) ^ (rhs.raw < 0);
+            let num = self.raw.unsigned_abs();
+            let den = rhs.raw.unsigned_abs();
+            let (hi, lo) = if FRAC == 0 {
+                (0, num)
+            } else {
+                (num >> (128 - FRAC), num << FRAC)
+            };
+            let (quo, overflow) = div_u256_by_u128(hi, lo, den);
+            let raw = if overflow || quo > i128::MAX as u128 { i128::MAX } else { quo as i128 };
+            FxBig { raw: if neg { -raw } else { raw } }
+        }
+    }
+
+    /**
+     * Implementation of ordering (the raw representation is monotone in the real value)
+     */
+    impl <const FRAC: u32> PartialOrd for FxBig<FRAC> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            self.raw.partial_cmp(&other.raw)
+        }
+    }
+
+    /**
+     * Implementation of displaying fixed-point numbers
+     */
+    impl <const FRAC: u32> fmt::Display for FxBig<FRAC> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.to_f64())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn div_keeps_precision_with_many_frac_bits() {
+            // regression: (a << FRAC) / b used to overflow i128 for large FRAC and return 0
+            let q = FxBig::<96>::from_f64(3.0) / FxBig::<96>::from_f64(2.0);
+            assert_eq!(q.to_f64(), 1.5);
+        }
+
+        #[test]
+        fn mul_round_trips_small_values() {
+            let p = FxBig::<96>::from_f64(1.5) * FxBig::<96>::from_f64(2.0);
+            assert_eq!(p.to_f64(), 3.0);
+        }
+
+        #[test]
+        fn div_saturates_when_quotient_overflows() {
+            // a tiny divisor pushes the quotient past i128, which must saturate, not wrap
+            let q = FxBig::<96>::from_f64(1.0) / FxBig::<96>::from_f64(1e-20);
+            assert_eq!(q.raw, i128::MAX);
+        }
+
+        #[test]
+        fn mul_saturates_instead_of_wrapping() {
+            // a product whose magnitude exceeds the representable range stays positive
+            let big = FxBig::<96>::from_f64(1e9);
+            assert!((big * big).raw > 0);
+        }
+    }
+}
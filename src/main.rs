@@ -1,19 +1,73 @@
 pub mod bitmap;
 pub mod complex;
+pub mod fixed;
 
 use std::env;
+use std::ops::{Add, Sub, Mul};
 
-use crate::bitmap::bitmap::{BitMap, Pixel};
+use crate::bitmap::bitmap::{BitMap, IndexedBitMap, Pixel};
 use crate::complex::complex::*;
+use crate::fixed::fixed::FxBig;
+
+/**
+ * Number of fractional bits used by the fixed-point scalar; chosen well past f64's
+ * mantissa so deep zooms stay sharp
+ */
+const FX_FRAC : u32 = 96;
+
+/**
+ * Scalar abstraction the Mandelbrot renderer is generic over: it must supply a zero, a
+ * way to build a coordinate from the integer pixel grid and the pixel size, a bound value
+ * from an f64, and the arithmetic/ordering the complex iteration needs
+ */
+trait Scalar: Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Clone + PartialOrd + Sized {
+    fn zero() -> Self;
+    fn from_coord(grid: i64, pixel_size: f64) -> Self;
+    fn from_f64(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Scalar for f64 {
+    fn zero() -> Self { 0.0 }
+    fn from_coord(grid: i64, pixel_size: f64) -> Self { grid as f64 * pixel_size }
+    fn from_f64(v: f64) -> Self { v }
+    fn to_f64(self) -> f64 { self }
+}
+
+impl <const FRAC: u32> Scalar for FxBig<FRAC> {
+    fn zero() -> Self { FxBig::from_int(0) }
+    fn from_coord(grid: i64, pixel_size: f64) -> Self { FxBig::from_int(grid as i128) * FxBig::from_f64(pixel_size) }
+    fn from_f64(v: f64) -> Self { FxBig::from_f64(v) }
+    fn to_f64(self) -> f64 { FxBig::to_f64(self) }
+}
+
+/**
+ * Runs the Mandelbrot escape test for point c; returns the iteration count at which
+ * the orbit escaped the bound together with the final squared module, or None if it
+ * stayed bounded for all max_iter iterations (i.e. is considered inside the set).
+ * Generic over the scalar type so colored plots can use the fixed-point deep-zoom path too.
+ */
+fn mandelbrot_escape<N: Scalar>(c: Complex<N>, bound: N, max_iter: usize) -> Option<(usize, f64)> {
+    let mut z = Complex::new(N::zero(), N::zero());
+    for n in 0 .. max_iter {
+        z = z.clone() * z + c.clone();
+        let module_sq = z.clone().module_sq();
+        if module_sq > bound {
+            return Some((n, module_sq.to_f64()));
+        }
+    }
+    None
+}
 
 /**
  * Checks if number c is inside Mandelbrot set, by checking if in max_iter iteratins
- * it will stay under bound 
+ * it will stay under bound. Generic over the scalar type so it works with both f64 and
+ * the fixed-point FxBig used for deep zooms.
  */
-fn mandelbrot(c: Complex<f64>, bound: f64, max_iter: usize) -> bool {
-    let mut z = Complex::new(0., 0.);
+fn mandelbrot<N: Scalar>(c: Complex<N>, bound: N, max_iter: usize) -> bool {
+    let mut z = Complex::new(N::zero(), N::zero());
     for _ in 0 .. max_iter {
-        z = z.clone() * z + c.clone(); 
+        z = z.clone() * z + c.clone();
         if z.clone().module_sq() > bound {
             return false;
         }
@@ -21,14 +75,22 @@ fn mandelbrot(c: Complex<f64>, bound: f64, max_iter: usize) -> bool {
     true
 }
 
+/**
+ * Computes the normalized (smooth) escape value for a point that escaped after n
+ * iterations with final squared module module_sq
+ */
+fn smooth_escape(n: usize, module_sq: f64) -> f64 {
+    n as f64 + 1.0 - module_sq.sqrt().ln().ln() / 2.0f64.ln()
+}
+
 /** 
  * Preapares geneartor for bitmap with Mandelbrot set with point (0, 0) in (cent_x, cent_y) pixel, and length of
  * pixels set to pixel_size
  */
-fn create_generator((cent_x, cent_y) : (i64, i64), pixel_size : f64, bound: f64, max_iter: usize) -> impl Fn(u32, u32) -> Pixel {
+fn create_generator<N: Scalar>((cent_x, cent_y) : (i64, i64), pixel_size : f64, bound: f64, max_iter: usize) -> impl Fn(u32, u32) -> Pixel {
     move |x, y| {
-        let c = Complex::new(x as f64 - cent_x as f64, y as f64 - cent_y as f64) * pixel_size;
-        if mandelbrot(c, bound, max_iter) {
+        let c = Complex::new(N::from_coord(x as i64 - cent_x, pixel_size), N::from_coord(y as i64 - cent_y, pixel_size));
+        if mandelbrot(c, N::from_f64(bound), max_iter) {
             Pixel::BLACK
         } else {
             Pixel::WHITE
@@ -36,19 +98,109 @@ fn create_generator((cent_x, cent_y) : (i64, i64), pixel_size : f64, bound: f64,
     }
 }
 
+/**
+ * Built-in grayscale palette ramping from black to white over 256 entries
+ */
+fn palette_grayscale() -> Vec<Pixel> {
+    (0 ..= 255).map(|v| Pixel::new(v, v, v)).collect()
+}
+
+/**
+ * Built-in "fire" palette ramping from black through red and yellow to white
+ */
+fn palette_fire() -> Vec<Pixel> {
+    (0 ..= 255).map(|v: i32| {
+        let red = (v * 3).min(255) as u8;
+        let green = ((v - 85) * 3).clamp(0, 255) as u8;
+        let blue = ((v - 170) * 3).clamp(0, 255) as u8;
+        Pixel::new(red, green, blue)
+    }).collect()
+}
+
+/**
+ * Preapares generator for a colored Mandelbrot plot. Escaping points have their smooth
+ * escape value mu mapped through index into the palette (CLUT); points that never escape
+ * are painted with interior.
+ */
+fn create_color_generator<N: Scalar>((cent_x, cent_y) : (i64, i64), pixel_size : f64, bound: f64, max_iter: usize,
+        palette: Vec<Pixel>, interior: Pixel, index: impl Fn(f64) -> usize) -> impl Fn(u32, u32) -> Pixel {
+    move |x, y| {
+        let c = Complex::new(N::from_coord(x as i64 - cent_x, pixel_size), N::from_coord(y as i64 - cent_y, pixel_size));
+        match mandelbrot_escape(c, N::from_f64(bound), max_iter) {
+            Some((n, module_sq)) => {
+                let mu = smooth_escape(n, module_sq);
+                palette[index(mu) % palette.len()].clone()
+            }
+            None => interior.clone(),
+        }
+    }
+}
+
+/**
+ * Preapares generator emitting palette indices (rather than pixels) for a colored plot,
+ * so the result can be stored as an IndexedBitMap and saved with RLE8 compression. The
+ * palette doubles as the CLUT; escaping points map through index, interior points map to
+ * the interior palette slot.
+ */
+fn create_color_index_generator<N: Scalar>((cent_x, cent_y) : (i64, i64), pixel_size : f64, bound: f64, max_iter: usize,
+        palette_len: usize, interior: u8, index: impl Fn(f64) -> usize) -> impl Fn(u32, u32) -> u8 {
+    move |x, y| {
+        let c = Complex::new(N::from_coord(x as i64 - cent_x, pixel_size), N::from_coord(y as i64 - cent_y, pixel_size));
+        match mandelbrot_escape(c, N::from_f64(bound), max_iter) {
+            Some((n, module_sq)) => (index(smooth_escape(n, module_sq)) % palette_len) as u8,
+            None => interior,
+        }
+    }
+}
+
 /**
  * Generates bitmap with fragment of Mandelbrot set plot
  */
-fn get_bitmap_mandelbrot((x1, y1): (f64, f64), (x2, y2): (f64, f64), pixel_size : f64, bound: f64, max_iter: usize) -> BitMap {
+fn get_bitmap_mandelbrot<N: Scalar>((x1, y1): (f64, f64), (x2, y2): (f64, f64), pixel_size : f64, bound: f64, max_iter: usize) -> BitMap {
+    let width = ((x2 - x1) / pixel_size).ceil() as u32;
+    let height = ((y2 - y1) / pixel_size).ceil() as u32;
+    let center = ((-x1 / pixel_size).round() as i64, (y2 / pixel_size).round() as i64);
+
+    BitMap::new_from_generator(&create_generator::<N>(center, pixel_size, bound, max_iter), width, height)
+}
+
+/**
+ * Generates bitmap with fragment of Mandelbrot set plot colored through the given palette
+ */
+fn get_bitmap_mandelbrot_color<N: Scalar>((x1, y1): (f64, f64), (x2, y2): (f64, f64), pixel_size : f64, bound: f64, max_iter: usize, palette: Vec<Pixel>) -> BitMap {
+    let width = ((x2 - x1) / pixel_size).ceil() as u32;
+    let height = ((y2 - y1) / pixel_size).ceil() as u32;
+    let center = ((-x1 / pixel_size).round() as i64, (y2 / pixel_size).round() as i64);
+
+    // spread the escape value across the palette so its whole range is used (and band/cycle
+    // through it for the higher escape counts deep near the boundary)
+    let gen = create_color_generator::<N>(center, pixel_size, bound, max_iter, palette, Pixel::BLACK, |mu| (mu * 8.0).max(0.0) as usize);
+    BitMap::new_from_generator(&gen, width, height)
+}
+
+/**
+ * Generates an indexed (paletted) bitmap of a Mandelbrot fragment, ready to be saved as a
+ * BI_RLE8-compressed .bmp. The interior maps to palette slot 0 (black in the built-ins).
+ */
+fn get_bitmap_mandelbrot_color_indexed<N: Scalar>((x1, y1): (f64, f64), (x2, y2): (f64, f64), pixel_size : f64, bound: f64, max_iter: usize, palette: Vec<Pixel>) -> IndexedBitMap {
     let width = ((x2 - x1) / pixel_size).ceil() as u32;
     let height = ((y2 - y1) / pixel_size).ceil() as u32;
-    let center = ((-x1 / pixel_size).round() as i64, (y2 / pixel_size).round() as i64); 
+    let center = ((-x1 / pixel_size).round() as i64, (y2 / pixel_size).round() as i64);
 
-    BitMap::new_from_generator(&create_generator(center, pixel_size, bound, max_iter), width, height)
+    let len = palette.len();
+    let gen = create_color_index_generator::<N>(center, pixel_size, bound, max_iter, len, 0, |mu| (mu * 8.0).max(0.0) as usize);
+    IndexedBitMap::new_from_generator(&gen, palette, width, height)
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let all_args: Vec<String> = env::args().collect();
+    // --fixed selects the fixed-point scalar for deep zooms; strip it so the remaining
+    // arguments keep their positional meaning
+    let use_fixed = all_args.contains(&String::from("--fixed"));
+    let use_color = all_args.contains(&String::from("--color"));
+    let use_gray = all_args.contains(&String::from("--gray"));
+    let use_rle = all_args.contains(&String::from("--rle"));
+    let args: Vec<String> = all_args.into_iter().filter(|a| a != "--fixed" && a != "--color" && a != "--gray" && a != "--rle").collect();
     if args.len() <= 1 || args.contains(&String::from("--help")) || args.contains(&String::from("/?")) {
         println!("Mandelbrot set drawer");
         println!("");
@@ -59,6 +211,10 @@ fn main() {
         println!("- <pixel_size>        - defines length of single pixel on plot (default=0.001)");
         println!("- <bound>             - defines what is maximal module of muber before being regected (default=2.0)");
         println!("- <max_iter>          - defines number of iteration in checking of pixel is in set (default=80)");
+        println!("- --fixed             - use high-precision fixed-point arithmetic instead of f64 (for deep zooms)");
+        println!("- --color             - color the plot by escape count instead of monochrome");
+        println!("- --gray              - with --color, use the grayscale palette instead of fire");
+        println!("- --rle               - with --color, save an RLE8-compressed indexed .bmp");
     } else {
         let file = &args[1];
 
@@ -111,10 +267,35 @@ fn main() {
         } else if bound <= 0.0 {
             println!("Wrong arguments: needs to be greater than 0, but {} <0 0", bound)
         } else {
-            let bm = get_bitmap_mandelbrot((x1, y1), (x2, y2), pixel_size, bound * bound, max_iter);
-            match bm.save_as_bmp(file) {
+            let bound_sq = bound * bound;
+            let save = |res: Result<(), String>| match res {
                 Ok(()) => print!("Plot generated"),
                 Err(e) => print!("Error occured during saving to file: {}", e)
+            };
+            if use_color {
+                let palette = if use_gray { palette_grayscale() } else { palette_fire() };
+                if use_rle {
+                    let bm = if use_fixed {
+                        get_bitmap_mandelbrot_color_indexed::<FxBig<FX_FRAC>>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter, palette)
+                    } else {
+                        get_bitmap_mandelbrot_color_indexed::<f64>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter, palette)
+                    };
+                    save(bm.save_as_rle_bmp(file));
+                } else {
+                    let bm = if use_fixed {
+                        get_bitmap_mandelbrot_color::<FxBig<FX_FRAC>>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter, palette)
+                    } else {
+                        get_bitmap_mandelbrot_color::<f64>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter, palette)
+                    };
+                    save(bm.save_as_bmp(file));
+                }
+            } else {
+                let bm = if use_fixed {
+                    get_bitmap_mandelbrot::<FxBig<FX_FRAC>>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter)
+                } else {
+                    get_bitmap_mandelbrot::<f64>((x1, y1), (x2, y2), pixel_size, bound_sq, max_iter)
+                };
+                save(bm.save_as_bmp(file));
             }
         }
     }